@@ -0,0 +1,4 @@
+mod protocol;
+pub mod timestamp;
+
+pub use protocol::*;
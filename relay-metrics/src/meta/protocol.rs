@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use relay_common::time::UnixTimestamp;
 use serde::{Deserialize, Serialize};
 
+use crate::meta::timestamp;
 use crate::MetricResourceIdentifier;
 
 /// A metric metadata item.
@@ -19,6 +20,67 @@ pub struct MetricMeta {
     pub mapping: HashMap<MetricResourceIdentifier<'static>, Vec<Item>>,
 }
 
+impl MetricMeta {
+    /// Merges the metadata from `other` into `self`, deduplicating [`Location`] entries
+    /// and collapsing repeated [`Item::Unknown`] entries per MRI.
+    ///
+    /// Returns `None` without modifying `self` if `other` was bucketed under a
+    /// different [`StartOfDayUnixTimestamp`].
+    pub fn merge(&mut self, other: MetricMeta) -> Option<()> {
+        self.merge_bounded(other, usize::MAX)
+    }
+
+    /// Like [`Self::merge`], but caps the number of distinct [`Location`]s retained per
+    /// MRI at `max_locations_per_mri`, dropping any further incoming locations once the
+    /// cap is reached.
+    pub fn merge_bounded(&mut self, other: MetricMeta, max_locations_per_mri: usize) -> Option<()> {
+        if self.timestamp != other.timestamp {
+            return None;
+        }
+
+        for (mri, items) in other.mapping {
+            let entry = self.mapping.entry(mri).or_default();
+            merge_items(entry, items, max_locations_per_mri);
+        }
+
+        Some(())
+    }
+}
+
+/// Merges `incoming` items into `existing`, deduplicating [`Location`]s and collapsing
+/// [`Item::Unknown`] into a single entry, up to `max_locations` distinct locations.
+fn merge_items(existing: &mut Vec<Item>, incoming: Vec<Item>, max_locations: usize) {
+    let mut has_unknown = false;
+    let mut locations: HashSet<Location> = HashSet::new();
+
+    existing.retain(|item| match item {
+        Item::Location(location) => locations.insert(location.clone()),
+        Item::Unknown => {
+            let first = !has_unknown;
+            has_unknown = true;
+            first
+        }
+    });
+
+    for item in incoming {
+        match item {
+            Item::Location(location) => {
+                if locations.len() >= max_locations {
+                    continue;
+                }
+                if locations.insert(location.clone()) {
+                    existing.push(Item::Location(location));
+                }
+            }
+            Item::Unknown if !has_unknown => {
+                has_unknown = true;
+                existing.push(Item::Unknown);
+            }
+            Item::Unknown => {}
+        }
+    }
+}
+
 /// A metadata item.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -60,23 +122,57 @@ pub struct Location {
 }
 
 /// A Unix timestamp that is truncated to the start of the day.
+///
+/// The day boundary is computed in a fixed UTC offset (`0` by default), but the
+/// timestamp itself is always stored and serialized as a UTC [`UnixTimestamp`].
+/// Two instances truncated under different offsets are never equal, even if they
+/// happen to resolve to the same underlying timestamp.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct StartOfDayUnixTimestamp(UnixTimestamp);
+pub struct StartOfDayUnixTimestamp {
+    ts: UnixTimestamp,
+    offset_seconds: i32,
+}
 
 impl StartOfDayUnixTimestamp {
-    /// Creates a new `StartOfDayUnixTimestamp` from a timestamp by truncating it.
+    /// Creates a new `StartOfDayUnixTimestamp` from a timestamp by truncating it to the
+    /// start of the day in UTC.
     ///
     /// May return none when passed an invalid date, but in practice this never fails
     /// since the [`UnixTimestamp`] is already sufficiently validated.
     pub fn new(ts: UnixTimestamp) -> Option<Self> {
+        Self::new_in_offset(ts, 0)
+    }
+
+    /// Creates a new `StartOfDayUnixTimestamp` from a timestamp by truncating it to the
+    /// start of the day in the given UTC offset, specified in seconds.
+    ///
+    /// The timestamp is shifted by the offset, truncated to `00:00:00`, and then shifted
+    /// back, so the resulting [`UnixTimestamp`] is always expressed in UTC.
+    ///
+    /// Returns `None` when passed an invalid date or an offset that shifts the timestamp
+    /// out of range.
+    pub fn new_in_offset(ts: UnixTimestamp, offset_seconds: i32) -> Option<Self> {
         let dt: DateTime<Utc> = DateTime::from_timestamp(ts.as_secs().try_into().ok()?, 0)?;
-        let beginning_of_day = dt.date_naive().and_hms_opt(0, 0, 0)?.and_utc();
-        Some(Self(UnixTimestamp::from_datetime(beginning_of_day)?))
+        let offset = Duration::seconds(offset_seconds.into());
+
+        let local = dt.checked_add_signed(offset)?;
+        let beginning_of_local_day = local.date_naive().and_hms_opt(0, 0, 0)?.and_utc();
+        let beginning_of_day = beginning_of_local_day.checked_sub_signed(offset)?;
+
+        Some(Self {
+            ts: UnixTimestamp::from_datetime(beginning_of_day)?,
+            offset_seconds,
+        })
     }
 
     /// Returns the underlying unix timestamp, truncated to the start of the day.
     pub fn as_timestamp(&self) -> UnixTimestamp {
-        self.0
+        self.ts
+    }
+
+    /// Returns the UTC offset, in seconds, this timestamp was truncated with.
+    pub fn offset_seconds(&self) -> i32 {
+        self.offset_seconds
     }
 }
 
@@ -84,7 +180,7 @@ impl std::ops::Deref for StartOfDayUnixTimestamp {
     type Target = UnixTimestamp;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.ts
     }
 }
 
@@ -93,17 +189,63 @@ impl Serialize for StartOfDayUnixTimestamp {
     where
         S: serde::Serializer,
     {
-        self.0.serialize(serializer)
+        // The common case (UTC, offset 0) keeps the historical bare-timestamp wire
+        // format for compatibility with existing consumers. Only a non-zero offset,
+        // which would otherwise be silently lost on a round trip, is spelled out as an
+        // object so it survives serialization (e.g. aggregator forwarding).
+        if self.offset_seconds == 0 {
+            self.ts.serialize(serializer)
+        } else {
+            use serde::ser::SerializeStruct;
+
+            let mut state = serializer.serialize_struct("StartOfDayUnixTimestamp", 2)?;
+            state.serialize_field("seconds", &self.ts)?;
+            state.serialize_field("offset_seconds", &self.offset_seconds)?;
+            state.end()
+        }
     }
 }
 
+/// A [`UnixTimestamp`] accepted through the lenient [`timestamp`] formats.
+struct LenientTimestamp(UnixTimestamp);
+
+impl<'de> Deserialize<'de> for LenientTimestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        timestamp::deserialize(deserializer).map(LenientTimestamp)
+    }
+}
+
+/// The wire representation accepted for [`StartOfDayUnixTimestamp`]: either a bare
+/// timestamp (offset defaults to `0`, the historical format), or an object carrying
+/// the offset the timestamp was truncated with.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StartOfDayWire {
+    Object {
+        seconds: LenientTimestamp,
+        #[serde(default)]
+        offset_seconds: i32,
+    },
+    Scalar(LenientTimestamp),
+}
+
 impl<'de> Deserialize<'de> for StartOfDayUnixTimestamp {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let ts = UnixTimestamp::deserialize(deserializer)?;
-        StartOfDayUnixTimestamp::new(ts)
+        let (ts, offset_seconds) = match StartOfDayWire::deserialize(deserializer)? {
+            StartOfDayWire::Object {
+                seconds,
+                offset_seconds,
+            } => (seconds.0, offset_seconds),
+            StartOfDayWire::Scalar(seconds) => (seconds.0, 0),
+        };
+
+        StartOfDayUnixTimestamp::new_in_offset(ts, offset_seconds)
             .ok_or_else(|| serde::de::Error::custom("invalid timestamp"))
     }
 }
@@ -114,6 +256,157 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_start_of_day_in_offset() {
+        // 2024-05-17T01:10:00Z
+        let ts = UnixTimestamp::from_secs(1715908200);
+
+        let utc = StartOfDayUnixTimestamp::new(ts).unwrap();
+        assert_eq!(utc.as_timestamp().as_secs(), 1715904000); // 2024-05-17T00:00:00Z
+
+        // UTC+02:00 shifts local time to 2024-05-17T03:10:00+02:00, so the local day
+        // still started at 2024-05-16T22:00:00Z.
+        let plus_two = StartOfDayUnixTimestamp::new_in_offset(ts, 2 * 3600).unwrap();
+        assert_eq!(plus_two.as_timestamp().as_secs(), 1715896800); // 2024-05-16T22:00:00Z
+        assert_eq!(plus_two.offset_seconds(), 2 * 3600);
+
+        assert_ne!(utc, plus_two);
+    }
+
+    #[test]
+    fn test_start_of_day_offset_survives_roundtrip() {
+        let ts = UnixTimestamp::from_secs(1715908200);
+        let plus_two = StartOfDayUnixTimestamp::new_in_offset(ts, 2 * 3600).unwrap();
+
+        let json = serde_json::to_string(&plus_two).unwrap();
+        assert_eq!(json, r#"{"seconds":1715896800,"offset_seconds":7200}"#);
+
+        let roundtripped: StartOfDayUnixTimestamp = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtripped, plus_two);
+        assert_eq!(roundtripped.offset_seconds(), 2 * 3600);
+
+        // A UTC bucket (2024-05-16T00:00:00Z, bare wire format) and the +02:00 bucket
+        // must stay distinct across a wire round trip (this is what `MetricMeta::merge`
+        // relies on).
+        let utc = StartOfDayUnixTimestamp::new(UnixTimestamp::from_secs(1715896800)).unwrap();
+        let utc_roundtripped: StartOfDayUnixTimestamp =
+            serde_json::from_str(&serde_json::to_string(&utc).unwrap()).unwrap();
+        assert_ne!(utc_roundtripped, roundtripped);
+    }
+
+    #[test]
+    fn test_merge_dedup() {
+        let mut a: MetricMeta = serde_json::from_str(
+            r#"{
+                "timestamp": 1715904000,
+                "mapping": {
+                    "d:custom/memory.allocations@allocations": [
+                        { "type": "location", "lineno": 1 },
+                        { "type": "unknown" }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let b: MetricMeta = serde_json::from_str(
+            r#"{
+                "timestamp": 1715904000,
+                "mapping": {
+                    "d:custom/memory.allocations@allocations": [
+                        { "type": "location", "lineno": 1 },
+                        { "type": "location", "lineno": 2 },
+                        { "type": "unknown" }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        a.merge(b).expect("same day bucket merges");
+
+        assert_json_snapshot!(a, @r###"
+        {
+          "timestamp": 1715904000,
+          "mapping": {
+            "d:custom/memory.allocations@allocations": [
+              {
+                "type": "location",
+                "lineno": 1
+              },
+              {
+                "type": "unknown"
+              },
+              {
+                "type": "location",
+                "lineno": 2
+              }
+            ]
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn test_merge_bounded_drops_extras_over_cap() {
+        let mut a: MetricMeta = serde_json::from_str(
+            r#"{
+                "timestamp": 1715904000,
+                "mapping": {
+                    "d:custom/memory.allocations@allocations": [
+                        { "type": "location", "lineno": 1 }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let b: MetricMeta = serde_json::from_str(
+            r#"{
+                "timestamp": 1715904000,
+                "mapping": {
+                    "d:custom/memory.allocations@allocations": [
+                        { "type": "location", "lineno": 2 },
+                        { "type": "location", "lineno": 3 }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        a.merge_bounded(b, 1).expect("same day bucket merges");
+
+        // The cap was already reached by the existing `lineno: 1` location, so both
+        // incoming locations are dropped rather than deduplicated.
+        assert_json_snapshot!(a, @r###"
+        {
+          "timestamp": 1715904000,
+          "mapping": {
+            "d:custom/memory.allocations@allocations": [
+              {
+                "type": "location",
+                "lineno": 1
+              }
+            ]
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn test_merge_rejects_different_day() {
+        let mut a = MetricMeta {
+            timestamp: StartOfDayUnixTimestamp::new(UnixTimestamp::from_secs(1715904000)).unwrap(),
+            mapping: HashMap::new(),
+        };
+        let b = MetricMeta {
+            timestamp: StartOfDayUnixTimestamp::new(UnixTimestamp::from_secs(1715990400)).unwrap(),
+            mapping: HashMap::new(),
+        };
+
+        assert!(a.merge(b).is_none());
+    }
+
     #[test]
     fn test_deserialize_null_context() {
         let json = r#"{
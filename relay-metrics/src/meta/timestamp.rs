@@ -0,0 +1,119 @@
+//! A lenient, multi-format serde module for [`UnixTimestamp`].
+//!
+//! Upstream SDKs represent timestamps in a few different shapes: integer or
+//! fractional seconds, integer milliseconds, or RFC 3339 strings. Attach this
+//! module to a field via `#[serde(with = "timestamp")]` to accept all of them
+//! on deserialization while always serializing as integer seconds. This
+//! mirrors the `timestamp` / `timestamp::milliseconds` modules in the `time`
+//! crate, but normalizes everything to our own [`UnixTimestamp`].
+
+use chrono::DateTime;
+use relay_common::time::UnixTimestamp;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Timestamps at or above this magnitude are assumed to be expressed in
+/// milliseconds rather than seconds.
+///
+/// This corresponds to the year 5138 in seconds, which is far beyond any
+/// timestamp we expect to see expressed in seconds.
+const MILLIS_THRESHOLD: f64 = 1e11;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Lenient {
+    Number(f64),
+    String(String),
+}
+
+/// Serializes a [`UnixTimestamp`] as integer seconds.
+pub fn serialize<S>(ts: &UnixTimestamp, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    ts.serialize(serializer)
+}
+
+/// Deserializes a [`UnixTimestamp`] from integer/float seconds, integer
+/// milliseconds, or an RFC 3339 / ISO 8601 date string.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<UnixTimestamp, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Lenient::deserialize(deserializer)? {
+        Lenient::Number(n) => {
+            if !n.is_finite() {
+                return Err(serde::de::Error::custom("invalid timestamp"));
+            }
+
+            let secs = if n.abs() >= MILLIS_THRESHOLD {
+                (n / 1_000.0).floor()
+            } else {
+                n.floor()
+            };
+
+            if secs < 0.0 {
+                return Err(serde::de::Error::custom("invalid timestamp"));
+            }
+
+            Ok(UnixTimestamp::from_secs(secs as u64))
+        }
+        Lenient::String(s) => {
+            let dt = DateTime::parse_from_rfc3339(&s)
+                .map_err(|_| serde::de::Error::custom("invalid timestamp"))?;
+
+            UnixTimestamp::from_datetime(dt.into())
+                .ok_or_else(|| serde::de::Error::custom("invalid timestamp"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(with = "super")] UnixTimestamp);
+
+    fn parse(json: &str) -> UnixTimestamp {
+        serde_json::from_str::<Wrapper>(json).unwrap().0
+    }
+
+    #[test]
+    fn test_deserialize_seconds() {
+        assert_eq!(parse("1715904000"), UnixTimestamp::from_secs(1715904000));
+        assert_eq!(parse("1715904000.5"), UnixTimestamp::from_secs(1715904000));
+    }
+
+    #[test]
+    fn test_deserialize_millis() {
+        assert_eq!(parse("1715904000000"), UnixTimestamp::from_secs(1715904000));
+    }
+
+    #[test]
+    fn test_deserialize_rfc3339() {
+        assert_eq!(
+            parse(r#""2024-05-17T00:00:00Z""#),
+            UnixTimestamp::from_secs(1715904000)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_invalid() {
+        assert!(serde_json::from_str::<Wrapper>(r#""not-a-timestamp""#).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_rejects_non_finite() {
+        // `serde_json` itself refuses to represent NaN/Infinity numbers, so exercise the
+        // deserializer directly as other self-describing formats (e.g. messagepack) do
+        // allow non-finite floats on the wire.
+        use serde::de::IntoDeserializer;
+        use serde::de::value::Error as ValueError;
+
+        let nan = IntoDeserializer::<ValueError>::into_deserializer(f64::NAN);
+        assert!(deserialize(nan).is_err());
+
+        let infinity = IntoDeserializer::<ValueError>::into_deserializer(f64::INFINITY);
+        assert!(deserialize(infinity).is_err());
+    }
+}